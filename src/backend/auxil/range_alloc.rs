@@ -1,5 +1,22 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
-use std::ops::{Add, AddAssign, Range, Sub};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Range, Rem, Sub};
+
+/// How `RangeAllocator::allocate_range` picks a free range to satisfy a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum AllocationStrategy {
+    /// Take the first free range that is large enough. Cheapest, but tends to
+    /// fragment the front of the heap over time.
+    FirstFit,
+    /// Take the smallest free range that is still large enough, to minimize leftover
+    /// fragmentation. The default.
+    #[default]
+    BestFit,
+    /// Take the largest free range. Useful for keeping large contiguous holes
+    /// available for big future allocations.
+    WorstFit,
+}
 
 #[derive(Derivative)]
 #[derivative(Debug)]
@@ -10,53 +27,198 @@ pub struct RangeAllocator<T> {
     /// Must be ordered with ascending range start to permit short circuiting allocation.
     /// No two ranges in this vec may overlap.
     free_ranges: Vec<Range<T>>,
+    /// Index of `free_ranges` by length, to find a best fit in O(log n) instead of
+    /// scanning the whole free list. Maps a free range's length to the set of starting
+    /// offsets of free ranges with that length. Kept in sync with `free_ranges` on every
+    /// mutation.
+    free_ranges_by_size: BTreeMap<T, BTreeSet<T>>,
+    /// The strategy `allocate_range` uses to pick among candidate free ranges.
+    strategy: AllocationStrategy,
 }
 
 impl<T> RangeAllocator<T>
 where
-    T: Clone + Copy + Add<Output = T> + AddAssign + Sub<Output = T> + Eq + PartialOrd + Debug,
+    T: Clone + Copy + Add<Output = T> + AddAssign + Sub<Output = T> + Eq + Ord + Debug,
 {
     pub fn new(range: Range<T>) -> Self {
-        RangeAllocator {
+        Self::with_strategy(range, AllocationStrategy::default())
+    }
+
+    /// Like `new`, but with an explicit `AllocationStrategy` instead of the default
+    /// best-fit behavior.
+    pub fn with_strategy(range: Range<T>, strategy: AllocationStrategy) -> Self {
+        let mut alloc = RangeAllocator {
             initial_range: range.clone(),
-            free_ranges: vec![range.clone()],
-        }
+            free_ranges: Vec::new(),
+            free_ranges_by_size: BTreeMap::new(),
+            strategy,
+        };
+        alloc.free_push(range);
+        alloc
     }
 
     pub fn initial_range(&self) -> Range<T> {
         self.initial_range.clone()
     }
 
+    /// All free ranges, in ascending offset order.
+    pub fn free_ranges(&self) -> impl Iterator<Item = Range<T>> + '_ {
+        self.free_ranges.iter().cloned()
+    }
+
+    /// Total free space, i.e. the sum of the lengths of all free ranges.
+    pub fn free_space(&self) -> T
+    where
+        T: Sum,
+    {
+        self.free_ranges.iter().map(|range| range.end - range.start).sum()
+    }
+
+    /// The single largest free range, if any space is free.
+    pub fn largest_free_range(&self) -> Option<Range<T>> {
+        self.free_ranges
+            .iter()
+            .cloned()
+            .max_by_key(|range| range.end - range.start)
+    }
+
+    /// Fragmentation ratio, `1 - (largest free range / total free space)`. `0.0` means all
+    /// free space is contiguous; it approaches `1.0` as free space is scattered across many
+    /// small ranges. GPU memory managers can use this to decide when to defragment or spill
+    /// a heap.
+    pub fn fragmentation(&self) -> f32
+    where
+        T: Sum + Into<u64>,
+    {
+        let total_free: u64 = self.free_space().into();
+        if total_free == 0 {
+            return 0.0;
+        }
+        let largest = self
+            .largest_free_range()
+            .expect("total_free > 0 implies a largest free range exists");
+        let largest_free: u64 = (largest.end - largest.start).into();
+        (1.0 - largest_free as f64 / total_free as f64) as f32
+    }
+
+    /// Insert `range` into `free_ranges` at `index`, keeping `free_ranges_by_size` in sync.
+    fn free_insert(&mut self, index: usize, range: Range<T>) {
+        let length = range.end - range.start;
+        self.free_ranges_by_size
+            .entry(length)
+            .or_default()
+            .insert(range.start);
+        self.free_ranges.insert(index, range);
+    }
+
+    /// Remove and return the free range at `index`, keeping `free_ranges_by_size` in sync.
+    fn free_remove(&mut self, index: usize) -> Range<T> {
+        let range = self.free_ranges.remove(index);
+        let length = range.end - range.start;
+        if let Some(offsets) = self.free_ranges_by_size.get_mut(&length) {
+            offsets.remove(&range.start);
+            if offsets.is_empty() {
+                self.free_ranges_by_size.remove(&length);
+            }
+        }
+        range
+    }
+
+    /// Append `range` to the end of `free_ranges`, keeping `free_ranges_by_size` in sync.
+    fn free_push(&mut self, range: Range<T>) {
+        let index = self.free_ranges.len();
+        self.free_insert(index, range);
+    }
+
+    /// Carve `range` out of the free list, pinning it as allocated, e.g. for a fixed
+    /// descriptor slot or a resource that must live at a caller-chosen offset. Fails if
+    /// `range` is not fully covered by a single free range (i.e. it's already partly
+    /// allocated).
+    pub fn reserve_range(&mut self, range: Range<T>) -> Result<(), ()> {
+        assert!(range.start < range.end);
+        for i in 0..self.free_ranges.len() {
+            let f = self.free_ranges[i].clone();
+            if f.start <= range.start && range.end <= f.end {
+                self.free_remove(i);
+                let mut insert_at = i;
+                if f.start < range.start {
+                    self.free_insert(insert_at, f.start..range.start);
+                    insert_at += 1;
+                }
+                if range.end < f.end {
+                    self.free_insert(insert_at, range.end..f.end);
+                }
+                return Ok(());
+            }
+        }
+        Err(())
+    }
+
     pub fn allocate_range(&mut self, length: T) -> Option<Range<T>> {
+        match self.strategy {
+            AllocationStrategy::BestFit => self.allocate_best_fit(length),
+            AllocationStrategy::FirstFit => self.allocate_first_fit(length),
+            AllocationStrategy::WorstFit => self.allocate_worst_fit(length),
+        }
+    }
+
+    /// Smallest free range that is still big enough to fit `length`, found in
+    /// O(log n) via the size-indexed free list instead of a linear scan of
+    /// `free_ranges`.
+    fn allocate_best_fit(&mut self, length: T) -> Option<Range<T>> {
+        let start = self
+            .free_ranges_by_size
+            .range(length..)
+            .next()
+            .and_then(|(_, offsets)| offsets.iter().next().cloned())?;
+        let index = self
+            .free_ranges
+            .binary_search_by(|range| range.start.cmp(&start))
+            .unwrap();
+        let range = self.free_remove(index);
+        if range.end - range.start > length {
+            self.free_insert(index, (start + length)..range.end);
+        }
+        Some(start..(start + length))
+    }
+
+    /// First free range (in ascending offset order) that is big enough to fit
+    /// `length`.
+    fn allocate_first_fit(&mut self, length: T) -> Option<Range<T>> {
+        let index = self
+            .free_ranges
+            .iter()
+            .position(|range| range.end - range.start >= length)?;
+        let range = self.free_remove(index);
+        if range.end - range.start > length {
+            self.free_insert(index, (range.start + length)..range.end);
+        }
+        Some(range.start..(range.start + length))
+    }
+
+    /// Largest free range that is big enough to fit `length`.
+    fn allocate_worst_fit(&mut self, length: T) -> Option<Range<T>> {
         let mut best_fit: Option<(usize, Range<T>)> = None;
         for (index, range) in self.free_ranges.iter().cloned().enumerate() {
             let range_length = range.end - range.start;
             if range_length < length {
                 continue;
-            } else if range_length == length {
-                // Found a perfect fit, so stop looking.
-                best_fit = Some((index, range));
-                break;
             }
             best_fit = Some(match best_fit {
                 Some((best_index, best_range)) => {
-                    // Find best fit for this allocation to reduce memory fragmentation.
-                    if range_length < best_range.end - best_range.start {
+                    if range_length > best_range.end - best_range.start {
                         (index, range)
                     } else {
-                        (best_index, best_range.clone())
+                        (best_index, best_range)
                     }
                 }
-                None => {
-                    (index, range.clone())
-                }
+                None => (index, range),
             });
         }
-        best_fit.map(|(index, range)| {
-            if range.end - range.start == length {
-                self.free_ranges.remove(index);
-            } else {
-                self.free_ranges[index].start += length;
+        best_fit.map(|(index, _)| {
+            let range = self.free_remove(index);
+            if range.end - range.start > length {
+                self.free_insert(index, (range.start + length)..range.end);
             }
             range.start..(range.start + length)
         })
@@ -66,14 +228,14 @@ where
         assert!(self.initial_range.start <= range.start && range.end <= self.initial_range.end);
         assert!(range.start < range.end);
         if self.free_ranges.len() == 0 {
-            self.free_ranges.push(range);
+            self.free_push(range);
             return Ok(());
         }
         // Input is within range, but before any empty ranges and not
         // adjacent to them.
         if self.free_ranges.len() > 0 {
             if self.free_ranges[0].start > range.end {
-                self.free_ranges.insert(0, range);
+                self.free_insert(0, range);
                 return Ok(());
             }
         }
@@ -81,7 +243,7 @@ where
         // adjacent to them.
         if let Some(last) = self.free_ranges.last().cloned() {
             if last.end < range.start {
-                self.free_ranges.push(range);
+                self.free_push(range);
                 return Ok(());
             }
         }
@@ -90,11 +252,15 @@ where
             // Input is immediately to the left of an existing empty range.
             if range.end == self.free_ranges[i].start {
                 // Extend this range
-                self.free_ranges[i].start = range.start;
+                let old = self.free_remove(i);
+                let mut merged = range.start..old.end;
                 // Merge this into an adjacent range to the left if necessary.
-                if i > 0 && self.free_ranges[i - 1].end == self.free_ranges[i].start {
-                    let r = self.free_ranges.remove(i);
-                    self.free_ranges[i - 1].end = r.end;
+                if i > 0 && self.free_ranges[i - 1].end == merged.start {
+                    let left = self.free_remove(i - 1);
+                    merged.start = left.start;
+                    self.free_insert(i - 1, merged);
+                } else {
+                    self.free_insert(i, merged);
                 }
                 return Ok(());
             }
@@ -102,15 +268,14 @@ where
             // Input is immediately to the right of an existing empty range.
             if range.start == self.free_ranges[i].end {
                 // Extend this range
-                self.free_ranges[i].end = range.end;
-
+                let old = self.free_remove(i);
+                let mut merged = old.start..range.end;
                 // Merge this into an adjacent range to the right if necessary.
-                if i + 1 != self.free_ranges.len()
-                    && self.free_ranges[i + 1].start == self.free_ranges[i].end
-                {
-                    let r = self.free_ranges.remove(i + 1);
-                    self.free_ranges[i].end = r.end;
+                if i < self.free_ranges.len() && self.free_ranges[i].start == merged.end {
+                    let right = self.free_remove(i);
+                    merged.end = right.end;
                 }
+                self.free_insert(i, merged);
                 return Ok(());
             }
 
@@ -119,7 +284,7 @@ where
                 && range.start > self.free_ranges[i].end
                 && range.end < self.free_ranges[i + 1].start
             {
-                self.free_ranges.insert(i + 1, range);
+                self.free_insert(i + 1, range);
                 return Ok(());
             }
         }
@@ -128,7 +293,179 @@ where
 
     pub fn reset(&mut self) {
         self.free_ranges.clear();
-        self.free_ranges.push(self.initial_range.clone());
+        self.free_ranges_by_size.clear();
+        self.free_push(self.initial_range.clone());
+    }
+
+    /// Extend the allocator's backing range so it covers up to `new_end`, for when the
+    /// underlying memory allocation has been resized rather than replaced. The newly
+    /// added tail `[initial_range.end, new_end)` becomes free space.
+    pub fn grow(&mut self, new_end: T) {
+        assert!(new_end >= self.initial_range.end);
+        let old_end = self.initial_range.end;
+        self.initial_range.end = new_end;
+        if new_end == old_end {
+            return;
+        }
+        match self.free_ranges.last().cloned() {
+            Some(last) if last.end == old_end => {
+                let index = self.free_ranges.len() - 1;
+                self.free_remove(index);
+                self.free_insert(index, last.start..new_end);
+            }
+            _ => self.free_push(old_end..new_end),
+        }
+    }
+
+    /// Like `grow`, but extends the allocator's backing range at the front, down to
+    /// `new_start`. The newly added head `[new_start, initial_range.start)` becomes
+    /// free space.
+    pub fn grow_front(&mut self, new_start: T) {
+        assert!(new_start <= self.initial_range.start);
+        let old_start = self.initial_range.start;
+        self.initial_range.start = new_start;
+        if new_start == old_start {
+            return;
+        }
+        match self.free_ranges.first().cloned() {
+            Some(first) if first.start == old_start => {
+                self.free_remove(0);
+                self.free_insert(0, new_start..first.end);
+            }
+            _ => self.free_insert(0, new_start..old_start),
+        }
+    }
+}
+
+impl<T> RangeAllocator<T>
+where
+    T: Clone
+        + Copy
+        + Add<Output = T>
+        + AddAssign
+        + Sub<Output = T>
+        + Rem<Output = T>
+        + Eq
+        + Ord
+        + Debug,
+{
+    /// Like `allocate_range`, but the returned range is guaranteed to start at a multiple of
+    /// `align`. Any padding needed to satisfy alignment is kept on the free list, so it can
+    /// still be used by later, smaller or differently-aligned allocations. Respects the
+    /// allocator's configured `AllocationStrategy`, same as `allocate_range`.
+    ///
+    /// `align` must be non-zero. `align == 1` is handled as a fast path through
+    /// `allocate_range` itself, so `BestFit` callers still get the O(log n)
+    /// size-indexed lookup instead of the linear scan the alignment math requires.
+    pub fn allocate_range_with_align(&mut self, length: T, align: T) -> Option<Range<T>>
+    where
+        T: Default + From<u8>,
+    {
+        assert_ne!(align, T::default(), "align must be non-zero");
+        if align == T::from(1u8) {
+            return self.allocate_range(length);
+        }
+        match self.strategy {
+            AllocationStrategy::BestFit => self.allocate_best_fit_with_align(length, align),
+            AllocationStrategy::FirstFit => self.allocate_first_fit_with_align(length, align),
+            AllocationStrategy::WorstFit => self.allocate_worst_fit_with_align(length, align),
+        }
+    }
+
+    /// Smallest free range (after alignment padding) that is still big enough to fit
+    /// `length`.
+    fn allocate_best_fit_with_align(&mut self, length: T, align: T) -> Option<Range<T>> {
+        let mut best_fit: Option<(usize, Range<T>, T)> = None;
+        for (index, range) in self.free_ranges.iter().cloned().enumerate() {
+            let aligned_start = Self::align_up(range.start, align);
+            if aligned_start + length > range.end {
+                continue;
+            }
+            let usable_length = range.end - aligned_start;
+            if usable_length == length {
+                // Found a perfect fit, so stop looking.
+                best_fit = Some((index, range, aligned_start));
+                break;
+            }
+            best_fit = Some(match best_fit {
+                Some((best_index, best_range, best_aligned)) => {
+                    let best_usable = best_range.end - best_aligned;
+                    // Find best fit for this allocation to reduce memory fragmentation.
+                    if usable_length < best_usable {
+                        (index, range, aligned_start)
+                    } else {
+                        (best_index, best_range, best_aligned)
+                    }
+                }
+                None => (index, range, aligned_start),
+            });
+        }
+        best_fit.map(|(index, range, aligned_start)| {
+            self.commit_aligned(index, range, aligned_start, length)
+        })
+    }
+
+    /// First free range (in ascending offset order) that is big enough to fit `length`
+    /// once aligned.
+    fn allocate_first_fit_with_align(&mut self, length: T, align: T) -> Option<Range<T>> {
+        for (index, range) in self.free_ranges.iter().cloned().enumerate() {
+            let aligned_start = Self::align_up(range.start, align);
+            if aligned_start + length > range.end {
+                continue;
+            }
+            return Some(self.commit_aligned(index, range, aligned_start, length));
+        }
+        None
+    }
+
+    /// Largest free range (after alignment padding) that is big enough to fit `length`.
+    fn allocate_worst_fit_with_align(&mut self, length: T, align: T) -> Option<Range<T>> {
+        let mut best_fit: Option<(usize, Range<T>, T)> = None;
+        for (index, range) in self.free_ranges.iter().cloned().enumerate() {
+            let aligned_start = Self::align_up(range.start, align);
+            if aligned_start + length > range.end {
+                continue;
+            }
+            let usable_length = range.end - aligned_start;
+            best_fit = Some(match best_fit {
+                Some((best_index, best_range, best_aligned)) => {
+                    let best_usable = best_range.end - best_aligned;
+                    if usable_length > best_usable {
+                        (index, range, aligned_start)
+                    } else {
+                        (best_index, best_range, best_aligned)
+                    }
+                }
+                None => (index, range, aligned_start),
+            });
+        }
+        best_fit.map(|(index, range, aligned_start)| {
+            self.commit_aligned(index, range, aligned_start, length)
+        })
+    }
+
+    /// Round `start` up to the next multiple of `align`.
+    fn align_up(start: T, align: T) -> T {
+        let padding = (align - start % align) % align;
+        start + padding
+    }
+
+    /// Carve `length` out of free range `index` (originally `range`, before removal)
+    /// starting at `aligned_start`, returning the head and tail alignment gaps to the
+    /// free list.
+    fn commit_aligned(&mut self, index: usize, range: Range<T>, aligned_start: T, length: T) -> Range<T> {
+        let alloc_end = aligned_start + length;
+        self.free_remove(index);
+        let mut insert_at = index;
+        if range.start < aligned_start {
+            // Leftover head gap caused by alignment padding stays free.
+            self.free_insert(insert_at, range.start..aligned_start);
+            insert_at += 1;
+        }
+        if alloc_end < range.end {
+            self.free_insert(insert_at, alloc_end..range.end);
+        }
+        aligned_start..alloc_end
     }
 }
 
@@ -223,4 +560,260 @@ mod tests {
         // because 9..10 is a perfect fit.
         assert_eq!(alloc.allocate_range(1), Some(9..10));
     }
+
+    #[test]
+    fn test_grow_extends_trailing_free_range() {
+        let mut alloc = RangeAllocator::new(0..10);
+        assert_eq!(alloc.allocate_range(4), Some(0..4));
+        // The trailing free range [4..10) touches the old end, so growing should
+        // extend it in place rather than adding a separate free range.
+        alloc.grow(20);
+        assert_eq!(alloc.initial_range(), 0..20);
+        assert_eq!(alloc.free_ranges, vec![4..20]);
+    }
+
+    #[test]
+    fn test_grow_pushes_new_free_range_when_tail_is_allocated() {
+        let mut alloc = RangeAllocator::new(0..10);
+        assert_eq!(alloc.allocate_range(10), Some(0..10));
+        // Nothing is free at the old end, so growing adds a brand new free range.
+        alloc.grow(20);
+        assert_eq!(alloc.initial_range(), 0..20);
+        assert_eq!(alloc.free_ranges, vec![10..20]);
+    }
+
+    #[test]
+    fn test_grow_front_extends_leading_free_range() {
+        let mut alloc = RangeAllocator::new(10..20);
+        // The leading (and only) free range [10..20) touches the old start, so
+        // growing the front should extend it in place.
+        alloc.grow_front(0);
+        assert_eq!(alloc.initial_range(), 0..20);
+        assert_eq!(alloc.free_ranges, vec![0..20]);
+    }
+
+    #[test]
+    fn test_grow_front_pushes_new_free_range_when_head_is_allocated() {
+        let mut alloc = RangeAllocator::new(10..20);
+        assert_eq!(alloc.allocate_range(5), Some(10..15));
+        // [10..15) is allocated, so the new head isn't adjacent to a free range and
+        // must be inserted separately, ahead of the existing [15..20).
+        alloc.grow_front(0);
+        assert_eq!(alloc.initial_range(), 0..20);
+        assert_eq!(alloc.free_ranges, vec![0..10, 15..20]);
+    }
+
+    #[test]
+    fn test_free_space_and_largest_free_range() {
+        let mut alloc = RangeAllocator::new(0..100);
+        assert_eq!(alloc.free_space(), 100);
+        assert_eq!(alloc.largest_free_range(), Some(0..100));
+        assert_eq!(alloc.allocate_range(20), Some(0..20));
+        assert_eq!(alloc.allocate_range(30), Some(20..50));
+        assert!(alloc.free_range(0..20).is_ok());
+        // Free ranges are now [0..20) and [50..100).
+        assert_eq!(alloc.free_space(), 70);
+        assert_eq!(alloc.largest_free_range(), Some(50..100));
+        assert_eq!(alloc.free_ranges().collect::<Vec<_>>(), vec![0..20, 50..100]);
+    }
+
+    #[test]
+    fn test_fragmentation_of_contiguous_free_space_is_zero() {
+        let alloc = RangeAllocator::new(0u32..100u32);
+        assert_eq!(alloc.fragmentation(), 0.0);
+    }
+
+    #[test]
+    fn test_fragmentation_of_fully_allocated_heap_is_zero() {
+        let mut alloc = RangeAllocator::new(0u32..100u32);
+        assert_eq!(alloc.allocate_range(100), Some(0..100));
+        assert_eq!(alloc.fragmentation(), 0.0);
+    }
+
+    #[test]
+    fn test_fragmentation_increases_as_free_space_scatters() {
+        let mut alloc = RangeAllocator::new(0u32..100u32);
+        assert_eq!(alloc.allocate_range(20), Some(0..20));
+        assert_eq!(alloc.allocate_range(20), Some(20..40));
+        assert!(alloc.free_range(0..20).is_ok());
+        // Free ranges are [0..20) and [40..100): 60 free out of 80, largest is 60.
+        assert_eq!(alloc.fragmentation(), 1.0 - 60.0 / 80.0);
+    }
+
+    #[test]
+    fn test_fragmentation_works_for_u64_offsets() {
+        // Regression test: `fragmentation` must compile and work for the realistic
+        // GPU buffer/device-memory offset type, not just types with a lossless
+        // `Into<f64>` conversion.
+        let mut alloc = RangeAllocator::new(0u64..100u64);
+        assert_eq!(alloc.allocate_range(20), Some(0..20));
+        assert_eq!(alloc.allocate_range(20), Some(20..40));
+        assert!(alloc.free_range(0..20).is_ok());
+        // Free ranges are [0..20) and [40..100): 60 free out of 80, largest is 60.
+        assert_eq!(alloc.fragmentation(), 1.0 - 60.0 / 80.0);
+    }
+
+    #[test]
+    fn test_allocation_strategy_default_is_best_fit() {
+        assert_eq!(AllocationStrategy::default(), AllocationStrategy::BestFit);
+    }
+
+    #[test]
+    fn test_first_fit_takes_first_large_enough_range() {
+        let mut alloc =
+            RangeAllocator::with_strategy(0..30, AllocationStrategy::FirstFit);
+        assert_eq!(alloc.allocate_range(10), Some(0..10));
+        assert_eq!(alloc.allocate_range(15), Some(10..25));
+        assert!(alloc.free_range(0..10).is_ok());
+        // 0..10 and 25..30 both fit a length-5 request; first-fit should take the
+        // earlier one even though 25..30 is the tighter fit.
+        assert_eq!(alloc.allocate_range(5), Some(0..5));
+    }
+
+    #[test]
+    fn test_worst_fit_takes_largest_range() {
+        let mut alloc =
+            RangeAllocator::with_strategy(0..30, AllocationStrategy::WorstFit);
+        assert_eq!(alloc.allocate_range(20), Some(0..20));
+        assert!(alloc.free_range(0..5).is_ok());
+        // Free ranges are now [0..5) and [20..30); worst-fit should take the larger
+        // [20..30) even though [0..5) would be a tighter fit.
+        assert_eq!(alloc.allocate_range(5), Some(20..25));
+        assert_eq!(alloc.free_ranges, vec![0..5, 25..30]);
+    }
+
+    #[test]
+    fn test_allocate_range_with_align_respects_first_fit() {
+        let mut alloc =
+            RangeAllocator::with_strategy(0..30, AllocationStrategy::FirstFit);
+        assert_eq!(alloc.allocate_range(3), Some(0..3));
+        // Free ranges are now [3..30); aligning to 16 skips ahead to 16..30, and
+        // first-fit still has to take this one since it's the only candidate.
+        assert_eq!(alloc.allocate_range_with_align(10, 16), Some(16..26));
+        assert_eq!(alloc.free_ranges, vec![3..16, 26..30]);
+    }
+
+    #[test]
+    fn test_allocate_range_with_align_respects_worst_fit() {
+        let mut alloc =
+            RangeAllocator::with_strategy(0..80, AllocationStrategy::WorstFit);
+        assert_eq!(alloc.allocate_range(18), Some(0..18));
+        assert!(alloc.free_range(0..16).is_ok());
+        assert_eq!(alloc.free_ranges, vec![0..16, 18..80]);
+        // Worst-fit with 16-byte alignment should still prefer the much larger
+        // [18..80) range (aligning up to 32) over the smaller, already-aligned
+        // [0..16) range.
+        assert_eq!(alloc.allocate_range_with_align(4, 16), Some(32..36));
+    }
+
+    #[test]
+    fn test_free_ranges_by_size_stays_in_sync_after_coalescing() {
+        let mut alloc = RangeAllocator::new(0..100);
+        assert_eq!(alloc.allocate_range(10), Some(0..10));
+        assert_eq!(alloc.allocate_range(10), Some(10..20));
+        assert_eq!(alloc.allocate_range(10), Some(20..30));
+        assert!(alloc.free_range(0..10).is_ok());
+        assert!(alloc.free_range(10..20).is_ok());
+        // Freeing two adjacent blocks coalesces them into a single 0..20 free range;
+        // the size index needs to drop the two stale 10-length entries and gain one
+        // 20-length entry, not just update `free_ranges`.
+        assert_eq!(alloc.free_ranges, vec![0..20, 30..100]);
+        assert_eq!(alloc.allocate_range(15), Some(0..15));
+    }
+
+    #[test]
+    fn test_allocate_range_picks_lowest_offset_among_equal_size_candidates() {
+        let mut alloc = RangeAllocator::new(0..20);
+        assert_eq!(alloc.allocate_range(5), Some(0..5));
+        assert_eq!(alloc.allocate_range(5), Some(5..10));
+        assert_eq!(alloc.allocate_range(5), Some(10..15));
+        // Free ranges are now [15..20). Free 5..10 too so there are two equal-size
+        // candidates in the same size bucket; it should hand back the lower offset.
+        assert!(alloc.free_range(5..10).is_ok());
+        assert_eq!(alloc.free_ranges, vec![5..10, 15..20]);
+        assert_eq!(alloc.allocate_range(5), Some(5..10));
+    }
+
+    #[test]
+    fn test_reserve_range_splits_free_range() {
+        let mut alloc = RangeAllocator::new(0..10);
+        // Pin 4..6 out of the single free range, leaving the head and tail behind.
+        assert!(alloc.reserve_range(4..6).is_ok());
+        assert_eq!(alloc.free_ranges, vec![0..4, 6..10]);
+        // The reserved span can no longer be handed out.
+        assert_eq!(alloc.allocate_range(10), None);
+        assert_eq!(alloc.allocate_range(4), Some(0..4));
+    }
+
+    #[test]
+    fn test_reserve_range_at_edges_leaves_no_empty_remnant() {
+        let mut alloc = RangeAllocator::new(0..10);
+        // Reserving a span flush with the start of a free range shouldn't leave a
+        // zero-length remnant behind.
+        assert!(alloc.reserve_range(0..4).is_ok());
+        assert_eq!(alloc.free_ranges, vec![4..10]);
+    }
+
+    #[test]
+    fn test_reserve_range_fails_on_already_allocated_span() {
+        let mut alloc = RangeAllocator::new(0..10);
+        assert_eq!(alloc.allocate_range(5), Some(0..5));
+        // 3..7 straddles the allocated [0..5) and the free [5..10), so no single free
+        // range covers it.
+        assert!(alloc.reserve_range(3..7).is_err());
+        assert_eq!(alloc.free_ranges, vec![5..10]);
+    }
+
+    #[test]
+    fn test_allocate_range_with_align_fast_path() {
+        let mut alloc = RangeAllocator::new(0..10);
+        // align == 1 behaves just like the unaligned allocator.
+        assert_eq!(alloc.allocate_range_with_align(4, 1), Some(0..4));
+        assert_eq!(alloc.free_ranges, vec![4..10]);
+    }
+
+    #[test]
+    fn test_allocate_range_with_align_one_dispatches_to_allocate_range() {
+        // Same tie-break scenario as `test_allocate_range_picks_lowest_offset_among_equal_size_candidates`:
+        // with `align == 1` the result and the whole free list afterwards must match
+        // what `allocate_range` gives. The aligned best-fit scan would also pick the
+        // lowest offset on a tie, so these assertions check the outcome, not which
+        // path produced it.
+        let mut alloc = RangeAllocator::new(0..20);
+        assert_eq!(alloc.allocate_range(5), Some(0..5));
+        assert_eq!(alloc.allocate_range(5), Some(5..10));
+        assert_eq!(alloc.allocate_range(5), Some(10..15));
+        assert!(alloc.free_range(5..10).is_ok());
+        assert_eq!(alloc.free_ranges, vec![5..10, 15..20]);
+        assert_eq!(alloc.allocate_range_with_align(5, 1), Some(5..10));
+        assert_eq!(alloc.free_ranges, vec![15..20]);
+    }
+
+    #[test]
+    fn test_allocate_range_with_align_leaves_head_gap() {
+        let mut alloc = RangeAllocator::new(0..100);
+        assert_eq!(alloc.allocate_range(3), Some(0..3));
+        // The only free range starts at 3, which isn't a multiple of 16, so the
+        // allocator must skip ahead to 16 and return the [3..16) head gap to the
+        // free list.
+        assert_eq!(alloc.allocate_range_with_align(10, 16), Some(16..26));
+        assert_eq!(alloc.free_ranges, vec![3..16, 26..100]);
+    }
+
+    #[test]
+    fn test_allocate_range_with_align_perfect_fit() {
+        let mut alloc = RangeAllocator::new(0..32);
+        assert_eq!(alloc.allocate_range(16), Some(0..16));
+        // The remaining free range [16..32) is already aligned and exactly the
+        // requested length, so it's consumed entirely with no leftover gap.
+        assert_eq!(alloc.allocate_range_with_align(16, 16), Some(16..32));
+        assert_eq!(alloc.free_ranges, vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "align must be non-zero")]
+    fn test_allocate_range_with_align_rejects_zero_align() {
+        let mut alloc = RangeAllocator::new(0..10);
+        let _ = alloc.allocate_range_with_align(4, 0);
+    }
 }